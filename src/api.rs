@@ -0,0 +1,266 @@
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::RwLock;
+use trust_dns_server::{
+    authority::{Catalog, ZoneType},
+    client::rr::RrKey,
+    proto::rr::{dnssec::SupportedAlgorithms, DNSClass, Name, Record},
+    store::in_memory::InMemoryAuthority,
+};
+
+use crate::records::{build_record_sets, bump_zone_serial, generate_soa, DNSName, RData};
+
+/// Zones currently held live, shareable with the DNS-serving `Catalog` and
+/// mutable through this API without restarting `ServerFuture`.
+#[derive(Clone, Default)]
+pub struct ZoneRegistry(pub Arc<RwLock<BTreeMap<Name, Arc<InMemoryAuthority>>>>);
+
+/// The `Catalog` the DNS server (`Handler`) answers queries from, shared with
+/// this API so that zones created/deleted here take effect without a restart.
+#[derive(Clone)]
+pub struct CatalogHandle(pub Arc<RwLock<Catalog>>);
+
+#[derive(Clone)]
+pub struct ApiState {
+    pub zones: ZoneRegistry,
+    pub catalog: CatalogHandle,
+    pub jwt_secret: Arc<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    ZoneAdmin,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    roles: Vec<Role>,
+    #[allow(dead_code)]
+    exp: usize,
+}
+
+fn authorize(headers: &HeaderMap, secret: &str, allowed: &[Role]) -> Result<(), StatusCode> {
+    let header = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?
+    .claims;
+
+    if claims.roles.iter().any(|r| allowed.contains(r)) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+#[derive(Serialize)]
+struct ZoneSummary {
+    name: String,
+}
+
+async fn list_zones(State(state): State<ApiState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(code) = authorize(&headers, &state.jwt_secret, &[Role::Admin, Role::ZoneAdmin]) {
+        return code.into_response();
+    }
+
+    let zones = state.zones.0.read().await;
+    let names: Vec<ZoneSummary> = zones
+        .keys()
+        .map(|n| ZoneSummary { name: n.to_string() })
+        .collect();
+
+    Json(names).into_response()
+}
+
+async fn create_zone(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(zone): Path<String>,
+) -> impl IntoResponse {
+    if let Err(code) = authorize(&headers, &state.jwt_secret, &[Role::Admin]) {
+        return code.into_response();
+    }
+
+    let name = match Name::parse(&zone, None) {
+        Ok(n) => n,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let mut rc = BTreeMap::default();
+    rc.insert(
+        RrKey::new(name.clone().into(), trust_dns_server::proto::rr::RecordType::SOA),
+        generate_soa(DNSName(name.clone()), 1),
+    );
+
+    let authority = match InMemoryAuthority::new(name.clone(), rc, ZoneType::Primary, false) {
+        Ok(a) => a,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let authority = Arc::new(authority);
+
+    state
+        .zones
+        .0
+        .write()
+        .await
+        .insert(name.clone(), authority.clone());
+
+    state
+        .catalog
+        .0
+        .write()
+        .await
+        .upsert(name.into(), Box::new(authority));
+
+    StatusCode::CREATED.into_response()
+}
+
+async fn delete_zone(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(zone): Path<String>,
+) -> impl IntoResponse {
+    if let Err(code) = authorize(&headers, &state.jwt_secret, &[Role::Admin]) {
+        return code.into_response();
+    }
+
+    let name = match Name::parse(&zone, None) {
+        Ok(n) => n,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let removed = state.zones.0.write().await.remove(&name);
+    state.catalog.0.write().await.remove(&name.into());
+
+    match removed {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordBase {
+    name: DNSName,
+    #[serde(default = "default_class")]
+    class: DNSClass,
+    ttl: u32,
+    #[serde(flatten)]
+    rdata: RData,
+}
+
+fn default_class() -> DNSClass {
+    DNSClass::IN
+}
+
+async fn add_record(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(zone): Path<String>,
+    Json(body): Json<RecordBase>,
+) -> impl IntoResponse {
+    if let Err(code) = authorize(&headers, &state.jwt_secret, &[Role::Admin, Role::ZoneAdmin]) {
+        return code.into_response();
+    }
+
+    let zone_name = match Name::parse(&zone, None) {
+        Ok(n) => n,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let zones = state.zones.0.read().await;
+    let authority = match zones.get(&zone_name) {
+        Some(a) => a.clone(),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let record_sets = match build_record_sets(&body.name, std::slice::from_ref(&body.rdata), body.ttl) {
+        Ok(rs) => rs,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let serial = authority.serial().await;
+
+    for rs in record_sets {
+        for record in rs.records(false, SupportedAlgorithms::new()) {
+            let mut record: Record = record.clone();
+            record.set_dns_class(body.class);
+            authority.upsert(record, serial + 1).await;
+        }
+    }
+
+    let mut rc = authority.records_mut().await;
+    bump_zone_serial(&DNSName(zone_name), &mut rc);
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn remove_record(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(zone): Path<String>,
+    Json(body): Json<RecordBase>,
+) -> impl IntoResponse {
+    if let Err(code) = authorize(&headers, &state.jwt_secret, &[Role::Admin, Role::ZoneAdmin]) {
+        return code.into_response();
+    }
+
+    let zone_name = match Name::parse(&zone, None) {
+        Ok(n) => n,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let zones = state.zones.0.read().await;
+    let authority = match zones.get(&zone_name) {
+        Some(a) => a.clone(),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let rdata = match body.rdata.to_trust_rdata() {
+        Ok(rdata) => rdata,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let rr_key = RrKey::new(body.name.0.clone().into(), body.rdata.record_type());
+    let mut record = Record::with(body.name.0.clone(), body.rdata.record_type(), body.ttl);
+    record.set_dns_class(body.class);
+    record.set_data(Some(rdata));
+
+    {
+        let mut rc = authority.records_mut().await;
+        if let Some(rs) = rc.get_mut(&rr_key) {
+            Arc::make_mut(rs).remove(&record, 1);
+        }
+        bump_zone_serial(&DNSName(zone_name), &mut rc);
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/zones", get(list_zones))
+        .route("/zones/:zone", post(create_zone).delete(delete_zone))
+        .route("/zones/:zone/records", post(add_record))
+        .route("/zones/:zone/records", delete(remove_record))
+        .with_state(state)
+}
@@ -0,0 +1,138 @@
+//! Top-level server configuration: listen sockets, protocols, timeouts, and
+//! where the zone records live. Loaded from the path given by `--config` or
+//! the `CONFIG_PATH` environment variable (default `examplens.yaml`).
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+use crate::records::Records;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Udp,
+    Tcp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    #[serde(default)]
+    pub dns_over_https: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListenerConfig {
+    pub address: SocketAddr,
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_tcp_timeout_secs() -> u64 {
+    60
+}
+
+fn default_ttl() -> u32 {
+    30
+}
+
+fn default_config_path() -> PathBuf {
+    PathBuf::from("examplens.yaml")
+}
+
+fn default_records_path() -> PathBuf {
+    PathBuf::from("records.yaml")
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    pub listeners: Vec<ListenerConfig>,
+    #[serde(default = "default_tcp_timeout_secs")]
+    pub tcp_timeout_secs: u64,
+    #[serde(default = "default_ttl")]
+    pub default_ttl: u32,
+    #[serde(default = "default_records_path")]
+    pub records_path: PathBuf,
+    /// Source addresses permitted to send RFC 2136 dynamic UPDATE requests.
+    #[serde(default)]
+    pub allowed_update_sources: Vec<IpAddr>,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            listeners: vec![
+                ListenerConfig {
+                    address: "127.0.0.1:5300".parse().unwrap(),
+                    protocol: Protocol::Udp,
+                    tls: None,
+                },
+                ListenerConfig {
+                    address: "127.0.0.1:5300".parse().unwrap(),
+                    protocol: Protocol::Tcp,
+                    tls: None,
+                },
+            ],
+            tcp_timeout_secs: default_tcp_timeout_secs(),
+            default_ttl: default_ttl(),
+            records_path: default_records_path(),
+            allowed_update_sources: Vec::new(),
+        }
+    }
+}
+
+/// Top-level config. `records` may be inlined here or, when absent, loaded
+/// from `service.records_path`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub service: ServiceConfig,
+    #[serde(default)]
+    pub records: Option<Records>,
+}
+
+impl Config {
+    pub fn load(path: &std::path::Path) -> Result<Self, anyhow::Error> {
+        let io = std::fs::OpenOptions::new().read(true).open(path)?;
+        Ok(serde_yaml::from_reader(io)?)
+    }
+
+    pub fn records(&self) -> Result<Records, anyhow::Error> {
+        match &self.records {
+            Some(records) => Ok(records.clone()),
+            None => {
+                let io = std::fs::OpenOptions::new()
+                    .read(true)
+                    .open(&self.service.records_path)?;
+                Ok(serde_yaml::from_reader(io)?)
+            }
+        }
+    }
+}
+
+/// Resolves the config path from `--config <path>` or `CONFIG_PATH`, falling
+/// back to `examplens.yaml` to match the server's prior hardcoded default.
+///
+/// This is deliberately a different default than `ServiceConfig::records_path`
+/// (`records.yaml`): when neither `--config`/`CONFIG_PATH` nor an inline
+/// `records` block is given, `Config::records()` falls back to re-reading
+/// `service.records_path` as a separate file, and it must not resolve to the
+/// config file itself.
+pub fn config_path() -> PathBuf {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            if let Some(path) = args.next() {
+                return PathBuf::from(path);
+            }
+        }
+    }
+
+    std::env::var("CONFIG_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| default_config_path())
+}
@@ -0,0 +1,492 @@
+//! Optional per-zone online signing: RRSIG/DNSKEY generation plus a real,
+//! served NSEC3 chain for authenticated denial of existence (RFC 5155).
+//!
+//! The stock `InMemoryAuthority::secure_zone` only ever builds and serves a
+//! plain RFC 4034 NSEC chain, which trivially permits zone walking. To get
+//! NSEC3 actually used for negative responses (not just present as inert
+//! zone data), `Nsec3Authority` wraps a signed `InMemoryAuthority` and
+//! overrides `get_nsec_records` - the one place a negative-response denial
+//! proof is selected - with NSEC3 closest-covering-proof lookups.
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use trust_dns_server::{
+    authority::{
+        AuthLookup, AuthorityObject, DnssecAuthority, LookupError, LookupObject, LookupOptions,
+        LookupRecords, MessageRequest, UpdateResult,
+    },
+    client::rr::{
+        dnssec::{tbs, Algorithm, KeyFormat, Nsec3HashAlgorithm, SigSigner},
+        LowerName, RrKey,
+    },
+    proto::{
+        rr::{
+            dnssec::rdata::{DNSSECRData, NSEC3, NSEC3PARAM, SIG},
+            DNSClass, Name, RData, Record, RecordSet, RecordType,
+        },
+        serialize::binary::BinEncoder,
+    },
+    server::RequestInfo,
+    store::in_memory::InMemoryAuthority,
+};
+
+use crate::records::DNSName;
+
+const BASE32HEX_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+/// Signatures are valid for this long from the moment they're generated;
+/// re-running `sign_zone` (e.g. on restart) simply re-signs with a fresh window.
+const SIGNATURE_VALIDITY_SECS: u64 = 30 * 24 * 3600;
+
+/// Per-zone DNSSEC configuration loaded from `examplens.yaml`/config.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DnssecConfig {
+    pub enabled: bool,
+    pub key_path: PathBuf,
+    #[serde(default = "default_iterations")]
+    pub nsec3_iterations: u16,
+    #[serde(default)]
+    pub nsec3_salt: Vec<u8>,
+    #[serde(default)]
+    pub opt_out: bool,
+}
+
+fn default_iterations() -> u16 {
+    10
+}
+
+/// Canonical-wire-form iterated SHA-1 hash used by NSEC3 (RFC 5155 §5).
+fn nsec3_hash(name: &Name, salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut wire = Vec::new();
+    {
+        let mut encoder = BinEncoder::new(&mut wire);
+        name.to_lowercase()
+            .emit_as_canonical(&mut encoder, true)
+            .expect("name always encodes");
+    }
+
+    let mut digest = Sha1::digest([wire.as_slice(), salt].concat());
+    for _ in 0..iterations {
+        digest = Sha1::digest([digest.as_slice(), salt].concat());
+    }
+
+    digest.to_vec()
+}
+
+/// RFC 4648 base32hex without padding, as used for NSEC3 owner labels.
+fn base32hex(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buf = 0u32;
+    let mut bits = 0u32;
+
+    for &byte in bytes {
+        buf = (buf << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32HEX_ALPHABET[((buf >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        out.push(BASE32HEX_ALPHABET[((buf << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    out
+}
+
+/// Inverse of `base32hex`: recovers the raw hash bytes from an NSEC3 owner
+/// label, used at query time to compare a queried name's hash against the
+/// hash chain.
+fn base32hex_decode(label: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut buf = 0u32;
+    let mut bits = 0u32;
+
+    for &c in label {
+        let value = BASE32HEX_ALPHABET
+            .iter()
+            .position(|&a| a.eq_ignore_ascii_case(&c))
+            .unwrap_or(0) as u32;
+        buf = (buf << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+        }
+    }
+
+    out
+}
+
+/// Builds one NSEC3 record per owner name in `names_with_types`, each
+/// pointing at the next hash in sorted order (wrapping at the end) and
+/// carrying the type bitmap of the RRs present at that name.
+fn build_nsec3_chain(
+    zone: &Name,
+    names_with_types: &BTreeMap<Name, Vec<RecordType>>,
+    config: &DnssecConfig,
+) -> Vec<Record> {
+    let mut hashed: Vec<(Vec<u8>, Name, Vec<RecordType>)> = names_with_types
+        .iter()
+        .map(|(name, types)| {
+            (
+                nsec3_hash(name, &config.nsec3_salt, config.nsec3_iterations),
+                name.clone(),
+                types.clone(),
+            )
+        })
+        .collect();
+    hashed.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut records = Vec::with_capacity(hashed.len());
+    for (i, (hash, _name, types)) in hashed.iter().enumerate() {
+        let next = &hashed[(i + 1) % hashed.len()].0;
+        let owner = Name::from_utf8(format!("{}.{}", base32hex(hash), zone)).unwrap();
+
+        // NSEC3's hash-algorithm field is its own single-valued IANA
+        // registry (SHA-1 is effectively the only assignment) and is
+        // unrelated to the zone's DNSKEY signing algorithm; `nsec3_hash`
+        // above hardcodes SHA-1, so this must match.
+        let nsec3 = NSEC3::new(
+            Nsec3HashAlgorithm::SHA1,
+            config.opt_out,
+            config.nsec3_iterations,
+            config.nsec3_salt.clone(),
+            next.clone(),
+            types.clone(),
+        );
+
+        let mut record = Record::with(owner, RecordType::NSEC3, 30);
+        record.set_data(Some(RData::DNSSEC(DNSSECRData::NSEC3(nsec3))));
+        records.push(record);
+    }
+
+    records
+}
+
+/// Signs `rr_set` in place with `signer`, appending the RRSIG to the set.
+/// Mirrors `InMemoryAuthority`'s own (private) per-RRset signing so records
+/// inserted after `secure_zone()` has already run - our NSEC3 chain - don't
+/// end up unsigned.
+fn sign_rrset(rr_set: &mut RecordSet, signer: &SigSigner, zone_class: DNSClass) -> Result<(), anyhow::Error> {
+    let inception = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+    let expiration = inception + SIGNATURE_VALIDITY_SECS as u32;
+
+    let records: Vec<Record> = rr_set.records_without_rrsigs().cloned().collect();
+    let tbs = tbs::rrset_tbs(
+        rr_set.name(),
+        zone_class,
+        rr_set.name().num_labels(),
+        rr_set.record_type(),
+        signer.algorithm(),
+        rr_set.ttl(),
+        expiration,
+        inception,
+        signer.calculate_key_tag()?,
+        signer.signer_name(),
+        &records,
+    )?;
+
+    let signature = signer.sign(&tbs)?;
+
+    let mut rrsig = Record::with(rr_set.name().clone(), RecordType::RRSIG, rr_set.ttl());
+    rrsig.set_data(Some(RData::DNSSEC(DNSSECRData::SIG(SIG::new(
+        rr_set.record_type(),
+        signer.algorithm(),
+        rr_set.name().num_labels(),
+        rr_set.ttl(),
+        expiration,
+        inception,
+        signer.calculate_key_tag()?,
+        signer.signer_name().clone(),
+        signature,
+    )))));
+    rr_set.insert_rrsig(rrsig);
+
+    Ok(())
+}
+
+/// Signs `authority`'s zone in place: loads the zone signing key from
+/// `config.key_path`, calls `secure_zone` for RRSIG/DNSKEY generation, then
+/// replaces the library's own NSEC chain with a signed NSEC3 chain plus
+/// NSEC3PARAM. Actually serving the NSEC3 chain for negative responses
+/// requires registering the zone via `Nsec3Authority` (see below) rather
+/// than the bare `Arc<InMemoryAuthority>`.
+///
+/// This only signs the zone as loaded at startup. Record mutations made
+/// afterward - via the REST API (`api::add_record`/`remove_record`) or an
+/// RFC 2136 UPDATE (`update::process_update`) - are not re-signed and do
+/// not update the NSEC3 chain; `InMemoryAuthority::upsert` will in fact
+/// regenerate and re-sign its own plain NSEC chain once zone signing keys
+/// are registered, which `Nsec3Authority` has no opportunity to intercept.
+/// Treat DNSSEC+NSEC3 zones as read-only after signing until incremental
+/// re-signing is implemented.
+pub async fn sign_zone(
+    authority: &InMemoryAuthority,
+    domain: &DNSName,
+    config: &DnssecConfig,
+) -> Result<(), anyhow::Error> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    // `SigSigner`/`KeyPair` aren't `Clone`, and `add_zone_signing_key` takes
+    // the signer by value, so we build two independent signers from the same
+    // on-disk key material rather than try to share one: one handed to the
+    // library for its own RRSIG/DNSKEY generation, one kept here to sign the
+    // NSEC3 chain we build ourselves below.
+    let load_signer = || -> Result<SigSigner, anyhow::Error> {
+        let key_bytes = std::fs::read(&config.key_path)?;
+        let key_pair = KeyFormat::Pkcs8
+            .decode_key(&key_bytes, None, Algorithm::ECDSAP256SHA256)
+            .map_err(|e| anyhow::anyhow!("failed to load DNSSEC signing key: {e}"))?;
+        let dnskey = key_pair
+            .to_dnskey(Algorithm::ECDSAP256SHA256)
+            .map_err(|e| anyhow::anyhow!("failed to derive DNSKEY from signing key: {e}"))?;
+        Ok(SigSigner::dnssec(
+            dnskey,
+            key_pair,
+            domain.0.clone(),
+            std::time::Duration::from_secs(SIGNATURE_VALIDITY_SECS),
+        ))
+    };
+
+    authority.add_zone_signing_key(load_signer()?).await?;
+    authority.secure_zone().await?;
+    let signer = load_signer()?;
+
+    let mut names_with_types: BTreeMap<Name, Vec<RecordType>> = BTreeMap::new();
+    for rrset in authority.records().await.values() {
+        // The NSEC RRsets are dropped below, so they stay out of the bitmaps.
+        if rrset.record_type() == RecordType::NSEC {
+            continue;
+        }
+        names_with_types
+            .entry(rrset.name().clone())
+            .or_default()
+            .push(rrset.record_type());
+    }
+    names_with_types
+        .entry(domain.0.clone())
+        .or_default()
+        .push(RecordType::NSEC3PARAM);
+
+    let nsec3_records = build_nsec3_chain(&domain.0, &names_with_types, config);
+
+    let mut records = authority.records_mut().await;
+
+    // The NSEC chain `secure_zone()` just generated/signed is only fit to be
+    // served via `Nsec3Authority::get_nsec_records` below, which returns
+    // NSEC3 proofs instead; leaving the plain NSEC RRsets around would let a
+    // client walk the zone by querying type NSEC directly, defeating the
+    // point of switching to NSEC3.
+    records.retain(|key, _| key.record_type != RecordType::NSEC);
+
+    for record in nsec3_records {
+        let key = RrKey::new(record.name().clone().into(), RecordType::NSEC3);
+        let rs = records
+            .entry(key)
+            .or_insert_with(|| Arc::new(RecordSet::new(record.name(), RecordType::NSEC3, 1)));
+        let rs = Arc::make_mut(rs);
+        rs.insert(record, 1);
+        sign_rrset(rs, &signer, DNSClass::IN)?;
+    }
+
+    let nsec3param = NSEC3PARAM::new(
+        Nsec3HashAlgorithm::SHA1,
+        config.opt_out,
+        config.nsec3_iterations,
+        config.nsec3_salt.clone(),
+    );
+    let mut param_record = Record::with(domain.0.clone(), RecordType::NSEC3PARAM, 30);
+    param_record.set_data(Some(RData::DNSSEC(DNSSECRData::NSEC3PARAM(nsec3param))));
+
+    let param_key = RrKey::new(domain.0.clone().into(), RecordType::NSEC3PARAM);
+    let mut param_rs = RecordSet::new(&domain.0, RecordType::NSEC3PARAM, 1);
+    param_rs.insert(param_record, 1);
+    sign_rrset(&mut param_rs, &signer, DNSClass::IN)?;
+    records.insert(param_key, Arc::new(param_rs));
+
+    Ok(())
+}
+
+fn nsec3_rdata(rr_set: &RecordSet) -> Option<&NSEC3> {
+    rr_set
+        .records_without_rrsigs()
+        .find_map(|record| match record.data() {
+            Some(RData::DNSSEC(DNSSECRData::NSEC3(nsec3))) => Some(nsec3),
+            _ => None,
+        })
+}
+
+fn nsec3_owner_hash(rr_set: &RecordSet) -> Option<Vec<u8>> {
+    rr_set.name().iter().next().map(base32hex_decode)
+}
+
+/// True if the NSEC3 record with owner hash `owner` and "next hashed owner"
+/// `next` covers `target` - i.e. `target` falls in the half-open [owner, next)
+/// interval of the hash ring, wrapping at the end of the chain. A record whose
+/// owner hash equals `target` therefore also matches.
+fn covers(owner: &[u8], next: &[u8], target: &[u8]) -> bool {
+    if owner < next {
+        owner <= target && target < next
+    } else {
+        owner <= target || target < next
+    }
+}
+
+fn closest_covering_nsec3(nsec3_sets: &[Arc<RecordSet>], target: &[u8]) -> Option<Arc<RecordSet>> {
+    nsec3_sets
+        .iter()
+        .find(
+            |rr_set| match (nsec3_owner_hash(rr_set), nsec3_rdata(rr_set)) {
+                (Some(owner), Some(nsec3)) => covers(&owner, nsec3.next_hashed_owner_name(), target),
+                _ => false,
+            },
+        )
+        .cloned()
+}
+
+/// Wraps a DNSSEC-signed `InMemoryAuthority` so that negative responses are
+/// proven with the zone's NSEC3 chain (built by `sign_zone`) instead of the
+/// library's own NSEC chain. Every other operation (lookup, search, AXFR,
+/// dynamic update) is delegated straight through.
+#[derive(Clone)]
+pub struct Nsec3Authority {
+    inner: Arc<InMemoryAuthority>,
+    salt: Vec<u8>,
+    iterations: u16,
+}
+
+impl Nsec3Authority {
+    pub fn new(inner: Arc<InMemoryAuthority>, salt: Vec<u8>, iterations: u16) -> Self {
+        Self {
+            inner,
+            salt,
+            iterations,
+        }
+    }
+}
+
+#[async_trait]
+impl AuthorityObject for Nsec3Authority {
+    fn box_clone(&self) -> Box<dyn AuthorityObject> {
+        Box::new(self.clone())
+    }
+
+    fn zone_type(&self) -> trust_dns_server::authority::ZoneType {
+        self.inner.zone_type()
+    }
+
+    fn is_axfr_allowed(&self) -> bool {
+        self.inner.is_axfr_allowed()
+    }
+
+    async fn update(&self, update: &MessageRequest) -> UpdateResult<bool> {
+        self.inner.update(update).await
+    }
+
+    fn origin(&self) -> &LowerName {
+        self.inner.origin()
+    }
+
+    async fn lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> Result<Box<dyn LookupObject>, LookupError> {
+        self.inner.lookup(name, rtype, lookup_options).await
+    }
+
+    async fn search(
+        &self,
+        request_info: RequestInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> Result<Box<dyn LookupObject>, LookupError> {
+        self.inner.search(request_info, lookup_options).await
+    }
+
+    async fn get_nsec_records(
+        &self,
+        name: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> Result<Box<dyn LookupObject>, LookupError> {
+        let nsec3_sets: Vec<Arc<RecordSet>> = self
+            .inner
+            .records()
+            .await
+            .values()
+            .filter(|rr_set| rr_set.record_type() == RecordType::NSEC3)
+            .cloned()
+            .collect();
+
+        let target = nsec3_hash(&Name::from(name.clone()), &self.salt, self.iterations);
+        let closest_proof = closest_covering_nsec3(&nsec3_sets, &target);
+
+        let closest_encloser = Name::from(name.base_name());
+        let wildcard_proof = if closest_encloser != Name::from(name.clone()) {
+            let labels: Vec<&[u8]> = std::iter::once(b"*".as_slice())
+                .chain(closest_encloser.iter())
+                .collect();
+            let wildcard_name = Name::from_labels(labels).unwrap();
+            let wildcard_hash = nsec3_hash(&wildcard_name, &self.salt, self.iterations);
+            closest_covering_nsec3(&nsec3_sets, &wildcard_hash)
+        } else {
+            None
+        };
+
+        let proofs = match (closest_proof, wildcard_proof) {
+            (Some(closest), Some(wildcard)) if !Arc::ptr_eq(&closest, &wildcard) => {
+                vec![wildcard, closest]
+            }
+            (Some(proof), _) | (None, Some(proof)) => vec![proof],
+            (None, None) => vec![],
+        };
+
+        Ok(Box::new(AuthLookup::from(LookupRecords::many(
+            lookup_options,
+            proofs,
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 5155 Appendix B.1 test vectors: zone "example.", salt "aabbccdd"
+    // (hex), 12 iterations, SHA-1.
+    const SALT: [u8; 4] = [0xaa, 0xbb, 0xcc, 0xdd];
+    const ITERATIONS: u16 = 12;
+
+    fn hashed_owner(name: &str) -> String {
+        base32hex(&nsec3_hash(&Name::parse(name, None).unwrap(), &SALT, ITERATIONS)).to_ascii_lowercase()
+    }
+
+    #[test]
+    fn nsec3_hash_matches_rfc5155_appendix_b_vectors() {
+        assert_eq!(hashed_owner("example."), "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom");
+        assert_eq!(hashed_owner("ns1.example."), "2t7b4g4vsa5smi47k61mv5bv1a22bojr");
+        assert_eq!(hashed_owner("ns2.example."), "q04jkcevqvmu85r014c7dkba38o0ji5r");
+        assert_eq!(hashed_owner("w.example."), "k8udemvp1j2f7eg6jebps17vp3n8i58h");
+        assert_eq!(hashed_owner("x.w.example."), "b4um86eghhds6nea196smvmlo4ors995");
+        assert_eq!(hashed_owner("x.y.w.example."), "2vptu5timamqttgl4luu9kg21e0aor3s");
+        assert_eq!(hashed_owner("y.w.example."), "ji6neoaepv8b5o6k4ev33abha8ht9fgc");
+        assert_eq!(hashed_owner("*.w.example."), "r53bq7cc2uvmubfu5ocmm6pers9tk9en");
+    }
+
+    #[test]
+    fn base32hex_decode_is_inverse_of_base32hex() {
+        let bytes = nsec3_hash(&Name::parse("example.", None).unwrap(), &SALT, ITERATIONS);
+        let encoded = base32hex(&bytes);
+        assert_eq!(base32hex_decode(encoded.as_bytes()), bytes);
+    }
+}
@@ -0,0 +1,126 @@
+//! Wraps the stock `Catalog` so RFC 2136 UPDATE messages are routed through
+//! our own prerequisite/update evaluation (see `update.rs`) and outbound AXFR
+//! is gated by a per-zone peer allow-list, while every other request is
+//! served exactly as before.
+use std::{collections::BTreeMap, net::IpAddr};
+use trust_dns_server::{
+    authority::MessageResponseBuilder,
+    proto::{
+        op::{Header, MessageType, OpCode, ResponseCode},
+        rr::{DNSClass, Name, RecordType},
+    },
+    server::{Request, RequestHandler, ResponseHandler, ResponseInfo},
+};
+
+use crate::{
+    api::{CatalogHandle, ZoneRegistry},
+    records::DNSName,
+    update::process_update,
+};
+
+pub struct Handler {
+    catalog: CatalogHandle,
+    zones: ZoneRegistry,
+    allowed_update_sources: Vec<IpAddr>,
+    axfr_acls: BTreeMap<Name, Vec<IpAddr>>,
+}
+
+impl Handler {
+    pub fn new(
+        catalog: CatalogHandle,
+        zones: ZoneRegistry,
+        allowed_update_sources: Vec<IpAddr>,
+        axfr_acls: BTreeMap<Name, Vec<IpAddr>>,
+    ) -> Self {
+        Self {
+            catalog,
+            zones,
+            allowed_update_sources,
+            axfr_acls,
+        }
+    }
+
+    /// Outbound AXFR is otherwise unrestricted once a zone's `allow_axfr`
+    /// flag is set on its authority; this enforces the peer allow-list the
+    /// library itself has no notion of.
+    fn axfr_allowed(&self, zone: &Name, src: IpAddr) -> bool {
+        self.axfr_acls
+            .get(zone)
+            .map(|peers| peers.contains(&src))
+            .unwrap_or(false)
+    }
+
+    async fn handle_update<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        response_handle: R,
+    ) -> ResponseInfo {
+        // RFC 2136 §2.3: the zone section carries exactly one entry, of
+        // class IN and type SOA. Anything else is malformed and must be
+        // rejected rather than guessed at.
+        let query = request.query();
+        if query.query_class() != DNSClass::IN || query.query_type() != RecordType::SOA {
+            return self.respond(request, response_handle, ResponseCode::FormErr).await;
+        }
+        let zone = DNSName(query.name().clone().into());
+
+        // UPDATE messages reuse the general wire layout: Zone section in
+        // place of Question, Prerequisite section in place of Answer, and
+        // Update section in place of Authority.
+        let code = process_update(
+            &self.zones,
+            &zone,
+            request.answers(),
+            request.name_servers(),
+            request.src().ip(),
+            &self.allowed_update_sources,
+        )
+        .await;
+
+        self.respond(request, response_handle, code).await
+    }
+
+    async fn respond<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+        code: ResponseCode,
+    ) -> ResponseInfo {
+        let mut header = Header::response_from_request(request.header());
+        header.set_message_type(MessageType::Response);
+        header.set_response_code(code);
+
+        let response = MessageResponseBuilder::from_message_request(request).build_no_records(header);
+        response_handle
+            .send_response(response)
+            .await
+            .unwrap_or_else(|_| ResponseInfo::from(header))
+    }
+}
+
+#[async_trait::async_trait]
+impl RequestHandler for Handler {
+    async fn handle_request<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        response_handle: R,
+    ) -> ResponseInfo {
+        if request.header().op_code() == OpCode::Update {
+            return self.handle_update(request, response_handle).await;
+        }
+
+        let query = request.query();
+        if query.query_type() == RecordType::AXFR
+            && !self.axfr_allowed(&query.name().clone().into(), request.src().ip())
+        {
+            return self.respond(request, response_handle, ResponseCode::Refused).await;
+        }
+
+        self.catalog
+            .0
+            .read()
+            .await
+            .handle_request(request, response_handle)
+            .await
+    }
+}
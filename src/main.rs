@@ -1,17 +1,12 @@
 use anyhow::anyhow;
-use serde::{de::Visitor, Deserialize, Serialize};
-use std::{
-    collections::BTreeMap,
-    net::{Ipv4Addr, SocketAddr},
-    sync::Arc,
-    time::Duration,
-};
+use rustls::{Certificate, PrivateKey};
+use std::{collections::BTreeMap, net::SocketAddr, path::Path, sync::Arc, time::Duration};
 use tokio::net::{TcpListener, UdpSocket};
 use trust_dns_resolver::config::NameServerConfigGroup;
 use trust_dns_server::{
     authority::Catalog,
     client::rr::RrKey,
-    proto::rr::{Name, Record, RecordSet},
+    proto::rr::Name,
     store::{
         forwarder::{ForwardAuthority, ForwardConfig},
         in_memory::InMemoryAuthority,
@@ -19,117 +14,76 @@ use trust_dns_server::{
     ServerFuture,
 };
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
-pub struct DNSName(Name);
-
-impl Serialize for DNSName {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&self.0.to_string())
-    }
-}
-
-struct DNSNameVisitor;
-
-impl Visitor<'_> for DNSNameVisitor {
-    type Value = DNSName;
-
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("expecting a DNS name")
-    }
-
-    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-    where
-        E: serde::de::Error,
-    {
-        Ok(DNSName(match Name::parse(v, None) {
-            Ok(res) => res,
-            Err(e) => return Err(serde::de::Error::custom(e)),
-        }))
-    }
-}
-
-impl<'de> Deserialize<'de> for DNSName {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        deserializer.deserialize_str(DNSNameVisitor)
-    }
-}
-
-#[derive(Clone, Default, Serialize, Deserialize)]
-pub struct Records(BTreeMap<DNSName, BTreeMap<DNSName, Ipv4Addr>>);
-
-fn generate_a(name: DNSName, address: Ipv4Addr) -> RecordSet {
-    let mut v4rs = RecordSet::new(&name.0, trust_dns_server::proto::rr::RecordType::A, 30);
-
-    let mut rec = Record::with(
-        name.0.clone(),
-        trust_dns_server::proto::rr::RecordType::A,
-        30,
-    );
-    rec.set_data(Some(trust_dns_server::proto::rr::RData::A(address)));
-
-    v4rs.insert(rec, 1);
-    v4rs
-}
-
-fn generate_soa(domain: DNSName) -> RecordSet {
-    let mut rs = RecordSet::new(&domain.0, trust_dns_server::proto::rr::RecordType::SOA, 30);
-
-    let mut rec = Record::with(
-        domain.0.clone(),
-        trust_dns_server::proto::rr::RecordType::SOA,
-        30,
-    );
-
-    rec.set_data(Some(trust_dns_server::proto::rr::RData::SOA(
-        trust_dns_server::proto::rr::rdata::SOA::new(
-            domain.0.clone(),
-            Name::from_utf8(format!("administrator.{}", domain.0)).unwrap(),
-            1,
-            60,
-            1,
-            120,
-            30,
-        ),
-    )));
-
-    rs.insert(rec, 1);
-    rs
-}
-
-fn generate_catalog(records: Records) -> Result<Catalog, anyhow::Error> {
+mod api;
+mod config;
+mod dnssec;
+mod handler;
+mod records;
+mod update;
+mod zonefile;
+
+use api::{ApiState, CatalogHandle, ZoneRegistry};
+use config::{Config, ListenerConfig, Protocol};
+use handler::Handler;
+use records::{build_record_sets, generate_soa, Records};
+use std::net::IpAddr;
+
+async fn generate_catalog(
+    records: Records,
+    default_ttl: u32,
+) -> Result<(Catalog, ZoneRegistry, BTreeMap<Name, Vec<IpAddr>>), anyhow::Error> {
     let mut catalog = Catalog::default();
+    let mut zones = BTreeMap::default();
+    let mut axfr_acls = BTreeMap::default();
 
-    for (domain, recs) in records.0 {
-        let mut rc = BTreeMap::default();
-        for (name, rec) in recs {
+    for (domain, zone_config) in records.0 {
+        let allow_axfr = !zone_config.allow_axfr.is_empty();
+
+        let authority = if let Some(path) = &zone_config.file {
+            Arc::new(zonefile::load(&domain.0, path, allow_axfr)?)
+        } else {
+            let mut rc = BTreeMap::default();
             rc.insert(
-                RrKey::new(
-                    domain.0.clone().into(),
-                    trust_dns_server::proto::rr::RecordType::SOA,
-                ),
-                generate_soa(domain.clone()),
+                RrKey::new(domain.0.clone().into(), trust_dns_server::proto::rr::RecordType::SOA),
+                generate_soa(domain.clone(), 1),
             );
 
-            let a_rec = generate_a(name.clone(), rec);
-
-            rc.insert(RrKey::new(name.0.into(), a_rec.record_type()), a_rec);
+            for (name, rdatas) in zone_config.records {
+                for rs in build_record_sets(&name, &rdatas, default_ttl).map_err(|e| anyhow!(e))? {
+                    rc.insert(RrKey::new(name.0.clone().into(), rs.record_type()), rs);
+                }
+            }
+
+            Arc::new(
+                InMemoryAuthority::new(
+                    domain.0.clone(),
+                    rc,
+                    trust_dns_server::authority::ZoneType::Primary,
+                    allow_axfr,
+                )
+                .unwrap(),
+            )
+        };
+
+        let dnssec_config = zone_config.dnssec.clone();
+        if let Some(dnssec_config) = &dnssec_config {
+            dnssec::sign_zone(&authority, &domain, dnssec_config).await?;
         }
 
-        let authority = InMemoryAuthority::new(
-            domain.0.clone().into(),
-            rc,
-            trust_dns_server::authority::ZoneType::Primary,
-            false,
-        )
-        .unwrap();
-
-        catalog.upsert(domain.0.into(), Box::new(Arc::new(authority)));
+        axfr_acls.insert(domain.0.clone(), zone_config.allow_axfr.clone());
+        zones.insert(domain.0.clone(), authority.clone());
+
+        match dnssec_config {
+            Some(dnssec_config) if dnssec_config.enabled => catalog.upsert(
+                domain.0.into(),
+                Box::new(dnssec::Nsec3Authority::new(
+                    authority,
+                    dnssec_config.nsec3_salt,
+                    dnssec_config.nsec3_iterations,
+                )),
+            ),
+            _ => catalog.upsert(domain.0.into(), Box::new(authority)),
+        }
     }
 
     let resolv = trust_dns_resolver::system_conf::read_system_conf()?;
@@ -154,24 +108,105 @@ fn generate_catalog(records: Records) -> Result<Catalog, anyhow::Error> {
 
     catalog.upsert(Name::root().into(), Box::new(Arc::new(forwarder)));
 
-    Ok(catalog)
+    Ok((
+        catalog,
+        ZoneRegistry(Arc::new(tokio::sync::RwLock::new(zones))),
+        axfr_acls,
+    ))
+}
+
+/// Loads a PEM certificate chain and PKCS#8 private key off disk into the
+/// types `ServerFuture`'s TLS/HTTPS listeners expect.
+fn load_tls_cert_key(cert_path: &Path, key_path: &Path) -> Result<(Vec<Certificate>, PrivateKey), anyhow::Error> {
+    let mut cert_reader = std::io::BufReader::new(std::fs::File::open(cert_path)?);
+    let certs = rustls_pemfile::certs(&mut cert_reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    let mut key_reader = std::io::BufReader::new(std::fs::File::open(key_path)?);
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader)?;
+    let key = PrivateKey(
+        keys.pop()
+            .ok_or_else(|| anyhow!("no PKCS#8 private key found in {}", key_path.display()))?,
+    );
+
+    Ok((certs, key))
+}
+
+/// Registers one configured listener against `sf`, choosing UDP/TCP/TLS/HTTPS
+/// based on `listener.protocol` and the presence of a `tls` block.
+async fn register_listener(
+    sf: &mut ServerFuture<Handler>,
+    listener: &ListenerConfig,
+    tcp_timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    match (&listener.tls, listener.protocol) {
+        (None, Protocol::Udp) => {
+            let udp = UdpSocket::bind(listener.address).await?;
+            sf.register_socket(udp);
+        }
+        (None, Protocol::Tcp) => {
+            let tcp = TcpListener::bind(listener.address).await?;
+            sf.register_listener(tcp, tcp_timeout);
+        }
+        (Some(tls), Protocol::Tcp) if tls.dns_over_https => {
+            let tcp = TcpListener::bind(listener.address).await?;
+            let certificate_and_key = load_tls_cert_key(&tls.cert_path, &tls.key_path)?;
+            sf.register_https_listener(tcp, tcp_timeout, certificate_and_key, "examplens".to_string())?;
+        }
+        (Some(tls), Protocol::Tcp) => {
+            let tcp = TcpListener::bind(listener.address).await?;
+            let certificate_and_key = load_tls_cert_key(&tls.cert_path, &tls.key_path)?;
+            sf.register_tls_listener(tcp, tcp_timeout, certificate_and_key)?;
+        }
+        (Some(_), Protocol::Udp) => {
+            return Err(anyhow!("DNS-over-TLS/HTTPS require protocol: tcp"));
+        }
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let mut f = std::fs::OpenOptions::new();
-    f.read(true);
-    let io = f.open("examplens.yaml")?;
+    let config = Config::load(&config::config_path())?;
+    let records = config.records()?;
+
+    let (catalog, zones, axfr_acls) = generate_catalog(records, config.service.default_ttl).await?;
+    let catalog = CatalogHandle(Arc::new(tokio::sync::RwLock::new(catalog)));
+
+    let jwt_secret = std::env::var("EXAMPLENS_JWT_SECRET")
+        .map_err(|_| anyhow!("EXAMPLENS_JWT_SECRET must be set to enable the management API"))?;
 
-    let records: Records = serde_yaml::from_reader(io)?;
+    let allowed_update_sources = config.service.allowed_update_sources.clone();
 
-    let sa = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 5300);
-    let tcp = TcpListener::bind(sa).await?;
-    let udp = UdpSocket::bind(sa).await?;
+    let api_state = ApiState {
+        zones: zones.clone(),
+        catalog: catalog.clone(),
+        jwt_secret: Arc::new(jwt_secret),
+    };
+
+    let api_addr: SocketAddr = std::env::var("EXAMPLENS_API_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:5380".to_string())
+        .parse()?;
+    tokio::spawn(async move {
+        if let Err(e) = axum::Server::bind(&api_addr)
+            .serve(api::router(api_state).into_make_service())
+            .await
+        {
+            eprintln!("management API exited: {e}");
+        }
+    });
+
+    let handler = Handler::new(catalog, zones, allowed_update_sources, axfr_acls);
+    let mut sf = ServerFuture::new(handler);
+
+    let tcp_timeout = Duration::new(config.service.tcp_timeout_secs, 0);
+    for listener in &config.service.listeners {
+        register_listener(&mut sf, listener, tcp_timeout).await?;
+    }
 
-    let mut sf = ServerFuture::new(generate_catalog(records)?);
-    sf.register_socket(udp);
-    sf.register_listener(tcp, Duration::new(60, 0));
     match sf.block_until_done().await {
         Ok(_) => Ok(()),
         Err(e) => Err(anyhow!(e)),
@@ -0,0 +1,273 @@
+use serde::{de::Visitor, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
+    sync::Arc,
+};
+use trust_dns_server::{
+    client::rr::RrKey,
+    proto::rr::{
+        dnssec::SupportedAlgorithms,
+        rdata,
+        rdata::{caa, txt},
+        Name, RData as TrustRData, Record, RecordSet, RecordType,
+    },
+};
+
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub struct DNSName(pub Name);
+
+impl Serialize for DNSName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+struct DNSNameVisitor;
+
+impl Visitor<'_> for DNSNameVisitor {
+    type Value = DNSName;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("expecting a DNS name")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(DNSName(match Name::parse(v, None) {
+            Ok(res) => res,
+            Err(e) => return Err(serde::de::Error::custom(e)),
+        }))
+    }
+}
+
+impl<'de> Deserialize<'de> for DNSName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DNSNameVisitor)
+    }
+}
+
+/// A single record value, tagged by type in the YAML (`type: A`, `type: CNAME`, ...).
+///
+/// Multiple `RData`s may be listed under the same owner name; `build_record_sets`
+/// groups them into one `RecordSet` per `RecordType`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[allow(clippy::upper_case_acronyms)] // variant names are the wire-format record type names
+pub enum RData {
+    A {
+        address: Ipv4Addr,
+    },
+    AAAA {
+        address: Ipv6Addr,
+    },
+    CNAME {
+        target: DNSName,
+    },
+    MX {
+        preference: u16,
+        mail_exchanger: DNSName,
+    },
+    NS {
+        target: DNSName,
+    },
+    TXT {
+        data: Vec<String>,
+    },
+    CAA {
+        issuer_critical: bool,
+        /// "issue", "issuewild", or "iodef" (RFC 8659 §4.1).
+        tag: String,
+        /// The CA domain (issue/issuewild) or report URL (iodef).
+        value: String,
+    },
+    PTR {
+        target: DNSName,
+    },
+    SOA {
+        mname: DNSName,
+        rname: DNSName,
+        serial: u32,
+        refresh: i32,
+        retry: i32,
+        expire: i32,
+        minimum: u32,
+    },
+}
+
+impl RData {
+    pub(crate) fn record_type(&self) -> RecordType {
+        match self {
+            RData::A { .. } => RecordType::A,
+            RData::AAAA { .. } => RecordType::AAAA,
+            RData::CNAME { .. } => RecordType::CNAME,
+            RData::MX { .. } => RecordType::MX,
+            RData::NS { .. } => RecordType::NS,
+            RData::TXT { .. } => RecordType::TXT,
+            RData::CAA { .. } => RecordType::CAA,
+            RData::PTR { .. } => RecordType::PTR,
+            RData::SOA { .. } => RecordType::SOA,
+        }
+    }
+
+    /// Converts to the wire `RData` trust-dns uses, rejecting malformed CAA
+    /// bodies (bad domain syntax, bad URL syntax) instead of panicking, since
+    /// this is reachable directly from the REST API with attacker-controlled
+    /// input (see `api::add_record`/`remove_record`).
+    pub(crate) fn to_trust_rdata(&self) -> Result<TrustRData, String> {
+        Ok(match self {
+            RData::A { address } => TrustRData::A(*address),
+            RData::AAAA { address } => TrustRData::AAAA(*address),
+            RData::CNAME { target } => TrustRData::CNAME(target.0.clone()),
+            RData::MX { preference, mail_exchanger } => {
+                TrustRData::MX(rdata::MX::new(*preference, mail_exchanger.0.clone()))
+            }
+            RData::NS { target } => TrustRData::NS(target.0.clone()),
+            RData::TXT { data } => TrustRData::TXT(txt::TXT::new(data.clone())),
+            RData::CAA {
+                issuer_critical,
+                tag,
+                value,
+            } => TrustRData::CAA(match tag.as_str() {
+                "issuewild" => caa::CAA::new_issuewild(
+                    *issuer_critical,
+                    Some(
+                        Name::parse(value, None)
+                            .map_err(|e| format!("CAA issuewild value must be a domain: {e}"))?,
+                    ),
+                    Vec::new(),
+                ),
+                "iodef" => caa::CAA::new_iodef(
+                    *issuer_critical,
+                    value
+                        .parse()
+                        .map_err(|e| format!("CAA iodef value must be a URL: {e}"))?,
+                ),
+                "issue" => caa::CAA::new_issue(
+                    *issuer_critical,
+                    Some(
+                        Name::parse(value, None)
+                            .map_err(|e| format!("CAA issue value must be a domain: {e}"))?,
+                    ),
+                    Vec::new(),
+                ),
+                other => {
+                    return Err(format!(
+                        "unknown CAA tag {other:?}, expected one of \"issue\", \"issuewild\", \"iodef\""
+                    ))
+                }
+            }),
+            RData::PTR { target } => TrustRData::PTR(target.0.clone()),
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => TrustRData::SOA(rdata::SOA::new(
+                mname.0.clone(),
+                rname.0.clone(),
+                *serial,
+                *refresh,
+                *retry,
+                *expire,
+                *minimum,
+            )),
+        })
+    }
+}
+
+/// A single zone, backed either by inline `records` or by a BIND-style
+/// master `file` (honoring that file's own SOA/serial), plus optional
+/// per-zone online-signing configuration and an AXFR peer allow-list.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ZoneConfig {
+    #[serde(default)]
+    pub records: BTreeMap<DNSName, Vec<RData>>,
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub dnssec: Option<crate::dnssec::DnssecConfig>,
+    #[serde(default)]
+    pub allow_axfr: Vec<IpAddr>,
+}
+
+/// `Records` maps zone apex -> that zone's configuration.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Records(pub BTreeMap<DNSName, ZoneConfig>);
+
+/// Groups `rdatas` by `RecordType` into one `RecordSet` per type, as is required
+/// when multiple distinct record types share an owner name (e.g. MX and TXT at
+/// the same host).
+pub fn build_record_sets(name: &DNSName, rdatas: &[RData], ttl: u32) -> Result<Vec<RecordSet>, String> {
+    let mut by_type: BTreeMap<RecordType, RecordSet> = BTreeMap::new();
+
+    for rdata in rdatas {
+        let record_type = rdata.record_type();
+        let rs = by_type
+            .entry(record_type)
+            .or_insert_with(|| RecordSet::new(&name.0, record_type, 1));
+        rs.set_ttl(ttl);
+
+        let mut rec = Record::with(name.0.clone(), record_type, ttl);
+        rec.set_data(Some(rdata.to_trust_rdata()?));
+        rs.insert(rec, 1);
+    }
+
+    Ok(by_type.into_values().collect())
+}
+
+pub fn generate_soa(domain: DNSName, serial: u32) -> RecordSet {
+    let mut rs = RecordSet::new(&domain.0, RecordType::SOA, 30);
+
+    let mut rec = Record::with(domain.0.clone(), RecordType::SOA, 30);
+
+    rec.set_data(Some(TrustRData::SOA(rdata::SOA::new(
+        domain.0.clone(),
+        Name::from_utf8(format!("administrator.{}", domain.0)).unwrap(),
+        serial,
+        60,
+        1,
+        120,
+        30,
+    ))));
+
+    rs.insert(rec, 1);
+    rs
+}
+
+/// Replaces a zone's SOA `RecordSet` with one carrying the next serial, as
+/// required after every successful RFC 2136 UPDATE.
+pub fn bump_serial(domain: &DNSName, current: &RecordSet) -> RecordSet {
+    let serial = current
+        .records(false, SupportedAlgorithms::new())
+        .find_map(|rec| match rec.data() {
+            Some(TrustRData::SOA(soa)) => Some(soa.serial()),
+            _ => None,
+        })
+        .unwrap_or(0);
+
+    generate_soa(domain.clone(), serial.wrapping_add(1))
+}
+
+/// Reinserts `zone`'s SOA `RecordSet` in `rc` with its serial bumped, as
+/// required after any mutation to the zone's records (RFC 2136 UPDATE, or
+/// the REST management API's record add/remove).
+pub fn bump_zone_serial(zone: &DNSName, rc: &mut BTreeMap<RrKey, Arc<RecordSet>>) {
+    let soa_key = RrKey::new(zone.0.clone().into(), RecordType::SOA);
+    if let Some(soa_rs) = rc.get(&soa_key) {
+        let bumped = bump_serial(zone, soa_rs);
+        rc.insert(soa_key, Arc::new(bumped));
+    }
+}
@@ -0,0 +1,275 @@
+//! RFC 2136 dynamic DNS UPDATE support, evaluated against the same
+//! `ZoneRegistry` the management API (see `api.rs`) mutates.
+use std::{collections::BTreeMap, net::IpAddr, sync::Arc};
+use trust_dns_server::{
+    client::rr::RrKey,
+    proto::{
+        op::ResponseCode,
+        rr::{dnssec::SupportedAlgorithms, DNSClass, Record, RecordSet, RecordType},
+    },
+};
+
+use crate::{
+    api::ZoneRegistry,
+    records::{bump_zone_serial, DNSName},
+};
+
+/// Evaluates the RFC 2136 §2.4 prerequisite section against the zone's
+/// current records. Class ANY/NONE carry the "exists"/"does not exist"
+/// semantics; any other class requires an exact RR match.
+fn check_prerequisites(
+    rc: &BTreeMap<RrKey, Arc<RecordSet>>,
+    prerequisites: &[Record],
+) -> Result<(), ResponseCode> {
+    for prereq in prerequisites {
+        let name = prereq.name();
+        let key = RrKey::new(name.clone().into(), prereq.record_type());
+
+        match (prereq.dns_class(), prereq.record_type()) {
+            (DNSClass::ANY, RecordType::ANY) => {
+                if !rc.keys().any(|k| k.name == name.clone().into()) {
+                    return Err(ResponseCode::NXDomain);
+                }
+            }
+            (DNSClass::ANY, _) => {
+                if !rc.contains_key(&key) {
+                    return Err(ResponseCode::NXRRSet);
+                }
+            }
+            (DNSClass::NONE, RecordType::ANY) => {
+                if rc.keys().any(|k| k.name == name.clone().into()) {
+                    return Err(ResponseCode::YXDomain);
+                }
+            }
+            (DNSClass::NONE, _) => {
+                if rc.contains_key(&key) {
+                    return Err(ResponseCode::YXRRSet);
+                }
+            }
+            _ => match rc.get(&key) {
+                Some(rs) if rs.records(false, SupportedAlgorithms::new()).any(|rr| rr == prereq) => {}
+                _ => return Err(ResponseCode::NXRRSet),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies the RFC 2136 §2.5 update section. Class ANY deletes (an RRset, or
+/// every RRset at a name when the type is also ANY); class NONE deletes one
+/// specific RR; any other class adds the RR.
+fn apply_update(rc: &mut BTreeMap<RrKey, Arc<RecordSet>>, updates: &[Record]) {
+    for rr in updates {
+        let name = rr.name();
+        let key = RrKey::new(name.clone().into(), rr.record_type());
+
+        match (rr.dns_class(), rr.record_type()) {
+            (DNSClass::ANY, RecordType::ANY) => {
+                rc.retain(|k, _| k.name != name.clone().into());
+            }
+            (DNSClass::ANY, _) => {
+                rc.remove(&key);
+            }
+            (DNSClass::NONE, _) => {
+                if let Some(rs) = rc.get_mut(&key) {
+                    Arc::make_mut(rs).remove(rr, 1);
+                }
+            }
+            _ => {
+                let rs = rc
+                    .entry(key)
+                    .or_insert_with(|| Arc::new(RecordSet::new(name, rr.record_type(), 1)));
+                Arc::make_mut(rs).insert(rr.clone(), 1);
+            }
+        }
+    }
+}
+
+/// Handles a single RFC 2136 UPDATE message for `zone`: the source address
+/// must be present in `allowed_sources`, every prerequisite must hold, and
+/// only then is the update section applied and the zone serial bumped.
+///
+/// Does not re-sign or rebuild the NSEC3 chain for DNSSEC-enabled zones
+/// (see `dnssec::sign_zone`'s doc comment) - avoid sending UPDATEs to such
+/// zones until incremental re-signing is implemented.
+pub async fn process_update(
+    zones: &ZoneRegistry,
+    zone: &DNSName,
+    prerequisites: &[Record],
+    updates: &[Record],
+    src: IpAddr,
+    allowed_sources: &[IpAddr],
+) -> ResponseCode {
+    if !allowed_sources.contains(&src) {
+        return ResponseCode::Refused;
+    }
+
+    let authority = {
+        let zones = zones.0.read().await;
+        match zones.get(&zone.0) {
+            Some(a) => a.clone(),
+            None => return ResponseCode::NotAuth,
+        }
+    };
+
+    let mut rc = authority.records_mut().await;
+
+    if let Err(code) = check_prerequisites(&rc, prerequisites) {
+        return code;
+    }
+
+    apply_update(&mut rc, updates);
+    bump_zone_serial(zone, &mut rc);
+
+    ResponseCode::NoError
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trust_dns_server::proto::rr::{Name, RData};
+
+    fn rc_with(records: Vec<Record>) -> BTreeMap<RrKey, Arc<RecordSet>> {
+        let mut rc = BTreeMap::default();
+        for record in records {
+            let key = RrKey::new(record.name().clone().into(), record.record_type());
+            let mut rs = RecordSet::new(record.name(), record.record_type(), 1);
+            rs.insert(record, 1);
+            rc.insert(key, Arc::new(rs));
+        }
+        rc
+    }
+
+    fn a_record(name: &str, addr: std::net::Ipv4Addr) -> Record {
+        let mut record = Record::with(Name::parse(name, None).unwrap(), RecordType::A, 30);
+        record.set_data(Some(RData::A(addr)));
+        record
+    }
+
+    fn prereq(name: &str, class: DNSClass, rtype: RecordType) -> Record {
+        let mut record = Record::with(Name::parse(name, None).unwrap(), rtype, 0);
+        record.set_dns_class(class);
+        record
+    }
+
+    #[test]
+    fn prerequisite_any_any_requires_name_to_exist() {
+        let rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::ANY, RecordType::ANY)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("missing.example.", DNSClass::ANY, RecordType::ANY)]),
+            Err(ResponseCode::NXDomain)
+        );
+    }
+
+    #[test]
+    fn prerequisite_any_rtype_requires_rrset_to_exist() {
+        let rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::ANY, RecordType::A)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::ANY, RecordType::AAAA)]),
+            Err(ResponseCode::NXRRSet)
+        );
+    }
+
+    #[test]
+    fn prerequisite_none_any_requires_name_to_be_absent() {
+        let rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("missing.example.", DNSClass::NONE, RecordType::ANY)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::NONE, RecordType::ANY)]),
+            Err(ResponseCode::YXDomain)
+        );
+    }
+
+    #[test]
+    fn prerequisite_none_rtype_requires_rrset_to_be_absent() {
+        let rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::NONE, RecordType::AAAA)]),
+            Ok(())
+        );
+        assert_eq!(
+            check_prerequisites(&rc, &[prereq("host.example.", DNSClass::NONE, RecordType::A)]),
+            Err(ResponseCode::YXRRSet)
+        );
+    }
+
+    #[test]
+    fn prerequisite_exact_rr_match_required() {
+        let existing = a_record("host.example.", "1.2.3.4".parse().unwrap());
+        let rc = rc_with(vec![existing.clone()]);
+
+        assert_eq!(check_prerequisites(&rc, &[existing]), Ok(()));
+        assert_eq!(
+            check_prerequisites(&rc, &[a_record("host.example.", "5.6.7.8".parse().unwrap())]),
+            Err(ResponseCode::NXRRSet)
+        );
+    }
+
+    #[test]
+    fn apply_update_any_any_deletes_every_rrset_at_name() {
+        let mut rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+        let mut aaaa = Record::with(
+            Name::parse("host.example.", None).unwrap(),
+            RecordType::AAAA,
+            30,
+        );
+        aaaa.set_data(Some(RData::AAAA("::1".parse().unwrap())));
+        Arc::make_mut(
+            rc.entry(RrKey::new(Name::parse("host.example.", None).unwrap().into(), RecordType::AAAA))
+                .or_insert_with(|| Arc::new(RecordSet::new(&Name::parse("host.example.", None).unwrap(), RecordType::AAAA, 1))),
+        )
+        .insert(aaaa, 1);
+
+        apply_update(&mut rc, &[prereq("host.example.", DNSClass::ANY, RecordType::ANY)]);
+
+        assert!(rc.is_empty());
+    }
+
+    #[test]
+    fn apply_update_any_rtype_deletes_one_rrset() {
+        let mut rc = rc_with(vec![a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        apply_update(&mut rc, &[prereq("host.example.", DNSClass::ANY, RecordType::A)]);
+
+        assert!(rc.is_empty());
+    }
+
+    #[test]
+    fn apply_update_none_removes_one_rr() {
+        let existing = a_record("host.example.", "1.2.3.4".parse().unwrap());
+        let mut rc = rc_with(vec![existing.clone()]);
+
+        let mut to_remove = existing;
+        to_remove.set_dns_class(DNSClass::NONE);
+        apply_update(&mut rc, &[to_remove]);
+
+        let key = RrKey::new(Name::parse("host.example.", None).unwrap().into(), RecordType::A);
+        assert_eq!(rc.get(&key).unwrap().records(false, SupportedAlgorithms::new()).count(), 0);
+    }
+
+    #[test]
+    fn apply_update_inserts_new_rr() {
+        let mut rc = BTreeMap::default();
+
+        apply_update(&mut rc, &[a_record("host.example.", "1.2.3.4".parse().unwrap())]);
+
+        let key = RrKey::new(Name::parse("host.example.", None).unwrap().into(), RecordType::A);
+        assert_eq!(rc.get(&key).unwrap().records(false, SupportedAlgorithms::new()).count(), 1);
+    }
+}
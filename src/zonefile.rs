@@ -0,0 +1,23 @@
+//! Loads a BIND-style RFC 1035 master zone file into an `InMemoryAuthority`,
+//! as an alternative to the inline YAML `records` schema in `records.rs`.
+use std::{fs, path::Path};
+use trust_dns_client::serialize::txt::{Lexer, Parser};
+use trust_dns_server::{
+    authority::ZoneType,
+    proto::rr::Name,
+    store::in_memory::InMemoryAuthority,
+};
+
+/// Parses `path` as a master zone file rooted at `origin`. The file's own
+/// SOA record (and therefore its serial) is kept as-is rather than being
+/// synthesized, unlike `records::generate_soa`.
+pub fn load(origin: &Name, path: &Path, allow_axfr: bool) -> Result<InMemoryAuthority, anyhow::Error> {
+    let contents = fs::read_to_string(path)?;
+
+    let (parsed_origin, records) = Parser::new()
+        .parse(Lexer::new(&contents), Some(origin.clone()), None)
+        .map_err(|e| anyhow::anyhow!("failed to parse zone file {}: {e}", path.display()))?;
+
+    InMemoryAuthority::new(parsed_origin, records, ZoneType::Primary, allow_axfr)
+        .map_err(|e| anyhow::anyhow!(e))
+}